@@ -9,6 +9,7 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use log::error;
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout},
@@ -17,9 +18,13 @@ use tui::{
     Frame, Terminal,
 };
 use chrono::Local;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
 
 use crate::config::AppConfig;
-use crate::trader::TokenPosition;
+use crate::trader::{self, TokenPosition};
+use crate::utils;
+use crate::wallet;
 
 #[derive(Parser)]
 #[command(name = "solana-pumpfun-sniper")]
@@ -31,11 +36,234 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Start the sniper bot
-    Start,
-    
-    /// Show wallet information
-    Wallet,
+    /// Watch Pump.fun for new tokens and auto-snipe them (the original
+    /// always-on loop), showing a live TUI dashboard
+    Monitor,
+
+    /// Buy a token manually
+    Buy {
+        /// Mint address of the token to buy
+        #[arg(long)]
+        mint: String,
+
+        /// Amount of SOL to spend
+        #[arg(long)]
+        amount: f64,
+
+        /// Slippage tolerance in basis points
+        #[arg(long, default_value_t = 50)]
+        slippage: u64,
+    },
+
+    /// Sell a percentage of a held token position
+    Sell {
+        /// Mint address of the token to sell
+        #[arg(long)]
+        mint: String,
+
+        /// Percentage of the held balance to sell (0-100)
+        #[arg(long, default_value_t = 100.0)]
+        percentage: f64,
+    },
+
+    /// List tracked positions with live profit/loss
+    Positions,
+
+    /// Show the wallet's SOL balance
+    Balance,
+
+    /// Wallet management
+    Wallet {
+        #[command(subcommand)]
+        action: WalletCommands,
+    },
+
+    /// Run the auto-snipe monitor loop headless, exposing list_positions/
+    /// buy/sell/balance/quit over a local JSON-RPC server instead of the TUI
+    Serve {
+        /// Address to bind the JSON-RPC server to
+        #[arg(long, default_value = "127.0.0.1:8899")]
+        bind: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WalletCommands {
+    /// Print the wallet's public key and SOL balance
+    Info,
+
+    /// Export the wallet's private key (base58) for backup
+    Export,
+}
+
+// Run the auto-snipe monitor loop alongside the live TUI dashboard
+pub async fn run_monitor(app_config: Arc<AppConfig>) -> Result<()> {
+    let active_tokens: Arc<Mutex<Vec<TokenPosition>>> = Arc::new(Mutex::new(utils::load_positions()?));
+
+    let monitor_config = app_config.clone();
+    let monitor_tokens = active_tokens.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::monitor::start_token_monitor(monitor_config, monitor_tokens).await {
+            error!("Token monitor exited with error: {}", e);
+        }
+    });
+
+    start_ui(app_config, active_tokens).await
+}
+
+// Run the auto-snipe monitor loop headless, driven over JSON-RPC instead of the TUI
+pub async fn run_serve(app_config: Arc<AppConfig>, bind: &str) -> Result<()> {
+    let active_tokens: Arc<Mutex<Vec<TokenPosition>>> = Arc::new(Mutex::new(utils::load_positions()?));
+
+    let monitor_config = app_config.clone();
+    let monitor_tokens = active_tokens.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::monitor::start_token_monitor(monitor_config, monitor_tokens).await {
+            error!("Token monitor exited with error: {}", e);
+        }
+    });
+
+    crate::server::run_server(app_config, active_tokens, bind).await
+}
+
+// Buy a token outright, bypassing the safety filters used by the monitor loop
+pub async fn run_buy(app_config: &AppConfig, mint: &str, amount: f64, slippage: u64) -> Result<()> {
+    let mint_address: Pubkey = mint.parse().context("Invalid mint address")?;
+
+    let buy_info = trader::buy_token(
+        &app_config.rpc_pool,
+        &app_config.keypair,
+        &mint_address,
+        amount,
+        slippage,
+        app_config.resolved_priority_fee(&app_config.rpc_url),
+        app_config.compute_unit_limit,
+        app_config.mock_swap,
+    ).await?;
+
+    println!(
+        "Bought {:.6} {} for {} SOL (tx {})",
+        buy_info.token_amount, mint, buy_info.sol_amount, buy_info.transaction_signature
+    );
+
+    // Track this manual buy the same way the `buy` JSON-RPC handler does
+    // (src/server.rs), so `positions`/the monitor loop see it too
+    let position = TokenPosition {
+        mint_address,
+        name: mint.to_string(),
+        symbol: mint.to_string(),
+        buy_price: buy_info.token_price,
+        buy_amount_sol: buy_info.sol_amount,
+        token_amount: buy_info.token_amount,
+        current_price: buy_info.token_price,
+        peak_price: buy_info.token_price,
+        buy_time: chrono::Utc::now(),
+        profit_target_1: app_config.profit_target_1 * buy_info.token_price,
+        profit_target_2: app_config.profit_target_2 * buy_info.token_price,
+        sold_percentage: 0.0,
+        last_updated: chrono::Utc::now(),
+        status: "Active".to_string(),
+    };
+
+    let mut positions = utils::load_positions()?;
+    positions.push(position);
+    utils::save_positions(&positions)?;
+
+    Ok(())
+}
+
+// Sell a percentage of the wallet's held balance of a token
+pub async fn run_sell(app_config: &AppConfig, mint: &str, percentage: f64) -> Result<()> {
+    let mint_address: Pubkey = mint.parse().context("Invalid mint address")?;
+
+    let token_balance = wallet::get_token_balance(
+        &app_config.rpc_pool,
+        &app_config.keypair.pubkey(),
+        &mint_address,
+    ).await?;
+
+    let sell_amount = (token_balance as f64 * (percentage / 100.0)) as u64;
+
+    let signature = trader::sell_token(
+        &app_config.rpc_pool,
+        &app_config.keypair,
+        &mint_address,
+        sell_amount,
+        app_config.slippage_bps,
+        app_config.resolved_priority_fee(&app_config.rpc_url),
+        app_config.compute_unit_limit,
+        app_config.mock_swap,
+    ).await?;
+
+    println!("Sold {}% of {} (tx {})", percentage, mint, signature);
+
+    // Update the tracked position's sold_percentage/status the same way the
+    // `sell` JSON-RPC handler does (src/server.rs), so a manual sell doesn't
+    // leave a stale "still fully held" position for the monitor loop to find
+    let mut positions = utils::load_positions()?;
+    if let Some(position) = positions.iter_mut().find(|p| p.mint_address == mint_address) {
+        position.sold_percentage = (position.sold_percentage + percentage).min(100.0);
+        if position.sold_percentage >= 100.0 {
+            position.status = "Fully Sold".to_string();
+        }
+        utils::save_positions(&positions)?;
+    }
+
+    Ok(())
+}
+
+// List the positions persisted by the monitor loop, refreshing each price
+pub async fn run_positions(app_config: &AppConfig) -> Result<()> {
+    let mut positions = utils::load_positions()?;
+
+    if positions.is_empty() {
+        println!("No tracked positions.");
+        return Ok(());
+    }
+
+    for position in positions.iter_mut() {
+        if let Ok(price) = trader::get_token_price(&app_config.rpc_pool, &position.mint_address).await {
+            position.current_price = price;
+        }
+
+        let (profit_amount, profit_percentage) = utils::calculate_profit(
+            position.buy_price,
+            position.current_price,
+            position.token_amount,
+        );
+
+        println!(
+            "{} ({}) - buy {:.6} SOL, now {:.6} SOL, P/L {:.6} SOL ({:.2}%) [{}]",
+            position.name,
+            position.symbol,
+            position.buy_price,
+            position.current_price,
+            profit_amount,
+            profit_percentage,
+            position.status
+        );
+    }
+
+    Ok(())
+}
+
+// Print the wallet's current SOL balance
+pub async fn run_balance(app_config: &AppConfig) -> Result<()> {
+    let balance = wallet::get_wallet_balance(&app_config.rpc_pool, &app_config.keypair.pubkey()).await?;
+    println!("{}: {:.9} SOL", app_config.keypair.pubkey(), balance);
+    Ok(())
+}
+
+// Dispatch `wallet` subcommands
+pub async fn run_wallet(app_config: &AppConfig, action: WalletCommands) -> Result<()> {
+    match action {
+        WalletCommands::Info => run_balance(app_config).await,
+        WalletCommands::Export => {
+            let private_key = bs58::encode(&app_config.keypair.to_bytes()[..32]).into_string();
+            println!("Private key (base58): {}", private_key);
+            Ok(())
+        }
+    }
 }
 
 // Start the TUI (Terminal User Interface)
@@ -91,11 +319,8 @@ async fn run_app<B: Backend>(
         
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        app.should_quit = true;
-                    }
-                    _ => {}
+                if let KeyCode::Char('q') = key.code {
+                    app.should_quit = true;
                 }
             }
         }
@@ -132,21 +357,15 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &AppState) {
     .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
     
-    // Create token table
-    let token_table = match tokio::task::block_in_place(|| {
+    // Create token table. `tokio::sync::Mutex::lock` only ever awaits (it
+    // has no fallible try-lock path here), so this never needs a fallback.
+    let token_table = tokio::task::block_in_place(|| {
         tokio::runtime::Handle::current().block_on(async {
             let tokens = app.active_tokens.lock().await;
             create_token_table(&tokens)
         })
-    }) {
-        Ok(table) => table,
-        Err(_) => {
-            // Fallback if we can't get the lock
-            let empty_tokens: Vec<TokenPosition> = Vec::new();
-            create_token_table(&empty_tokens)
-        }
-    };
-    
+    });
+
     f.render_widget(token_table, chunks[1]);
     
     // Footer with instructions
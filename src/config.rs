@@ -1,6 +1,8 @@
 use std::env;
 use anyhow::{Result, Context};
 use dotenv::dotenv;
+use log::warn;
+use solana_client::rpc_client::RpcClient;
 use solana_sdk::signature::Keypair;
 
 // Pump.fun program ID from the reference documentation
@@ -10,18 +12,32 @@ pub const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6
 pub struct Config {
     pub rpc_url: String,
     pub ws_url: String,
+    pub rpc_endpoints: Vec<String>,
+    pub parallel_rpc_requests: usize,
     pub buy_amount_sol: f64,
     pub slippage_bps: u64,
     pub profit_target_1: f64,
     pub profit_target_2: f64,
     pub sell_percentage_1: f64,
     pub sell_percentage_2: f64,
+    pub stop_loss_ratio: f64,
+    pub trailing_activation: f64,
+    pub trailing_drawdown_pct: f64,
+    pub priority_fee_microlamports: u64,
+    pub compute_unit_limit: u32,
+    pub dynamic_priority_fee: bool,
+    pub max_concurrent_positions: usize,
+    pub max_total_sol_at_risk: f64,
+    pub min_wallet_balance_sol: f64,
+    pub mock_swap: bool,
+    pub server_auth_token: Option<String>,
 }
 
 // Application state that combines configuration and runtime components
 pub struct AppConfig {
     pub rpc_url: String,
     pub ws_url: String,
+    pub rpc_pool: std::sync::Arc<crate::rpc_pool::RpcPool>,
     pub keypair: Keypair,
     pub buy_amount_sol: f64,
     pub slippage_bps: u64,
@@ -29,6 +45,49 @@ pub struct AppConfig {
     pub profit_target_2: f64,
     pub sell_percentage_1: f64,
     pub sell_percentage_2: f64,
+    pub stop_loss_ratio: f64,
+    pub trailing_activation: f64,
+    pub trailing_drawdown_pct: f64,
+    pub priority_fee_microlamports: u64,
+    pub compute_unit_limit: u32,
+    pub dynamic_priority_fee: bool,
+    pub max_concurrent_positions: usize,
+    pub max_total_sol_at_risk: f64,
+    pub min_wallet_balance_sol: f64,
+    pub mock_swap: bool,
+    pub server_auth_token: Option<String>,
+}
+
+impl AppConfig {
+    // Resolve the compute-unit price to bid: either the configured static
+    // value, or a percentile of recent network prioritization fees when
+    // `DYNAMIC_PRIORITY_FEE` is enabled
+    pub fn resolved_priority_fee(&self, rpc_url: &str) -> u64 {
+        if !self.dynamic_priority_fee {
+            return self.priority_fee_microlamports;
+        }
+
+        let rpc_client = RpcClient::new(rpc_url.to_string());
+        match crate::utils::recent_priority_fee_microlamports(&rpc_client, 75) {
+            Ok(fee) => fee,
+            Err(e) => {
+                warn!("Falling back to static priority fee: {}", e);
+                self.priority_fee_microlamports
+            }
+        }
+    }
+}
+
+// Construct a WebSocket URL from an RPC URL by swapping the scheme
+pub fn derive_ws_url(rpc_url: &str) -> String {
+    if rpc_url.starts_with("https://") {
+        rpc_url.replace("https://", "wss://")
+    } else if rpc_url.starts_with("http://") {
+        rpc_url.replace("http://", "ws://")
+    } else {
+        // Default to mainnet WebSocket if RPC URL doesn't have expected prefix
+        "wss://api.mainnet-beta.solana.com".to_string()
+    }
 }
 
 // Load configuration from environment variables
@@ -39,17 +98,32 @@ pub fn load_config() -> Result<Config> {
     // Get RPC URL with fallback
     let rpc_url = env::var("SOLANA_RPC_URL")
         .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
-    
-    // Construct WebSocket URL from RPC URL by replacing http with ws
-    let ws_url = if rpc_url.starts_with("https://") {
-        rpc_url.replace("https://", "wss://")
-    } else if rpc_url.starts_with("http://") {
-        rpc_url.replace("http://", "ws://")
-    } else {
-        // Default to mainnet WebSocket if RPC URL doesn't have expected prefix
-        "wss://api.mainnet-beta.solana.com".to_string()
+
+    let ws_url = derive_ws_url(&rpc_url);
+
+    // Additional RPC endpoints to spread load and fail over across, as a
+    // comma-separated list; the primary SOLANA_RPC_URL is always included
+    let rpc_endpoints = {
+        let mut endpoints = vec![rpc_url.clone()];
+        if let Ok(extra) = env::var("RPC_ENDPOINTS") {
+            endpoints.extend(extra.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
+        }
+        // `Vec::dedup` only removes *consecutive* duplicates, so a
+        // non-adjacent repeat of the primary URL (or of another endpoint)
+        // would survive and let `parallel_rpc_requests` race the same
+        // endpoint against itself. Track seen endpoints in a HashSet
+        // instead, preserving the original (primary-first) order.
+        let mut seen = std::collections::HashSet::with_capacity(endpoints.len());
+        endpoints.retain(|endpoint| seen.insert(endpoint.clone()));
+        endpoints
     };
-    
+
+    // How many of those endpoints to race a single call across at once
+    let parallel_rpc_requests = env::var("PARALLEL_RPC_REQUESTS")
+        .unwrap_or_else(|_| "2".to_string())
+        .parse::<usize>()
+        .context("Invalid PARALLEL_RPC_REQUESTS value")?;
+
     // Get trade parameters with defaults
     let buy_amount_sol = env::var("BUY_AMOUNT_SOL")
         .unwrap_or_else(|_| "0.1".to_string())
@@ -82,16 +156,96 @@ pub fn load_config() -> Result<Config> {
         .unwrap_or_else(|_| "100.0".to_string())
         .parse::<f64>()
         .context("Invalid SELL_PERCENTAGE_2 value")?;
-    
+
+    // Downside protection: hard stop-loss, expressed as a fraction of the buy price
+    let stop_loss_ratio = env::var("STOP_LOSS_RATIO")
+        .unwrap_or_else(|_| "0.5".to_string())
+        .parse::<f64>()
+        .context("Invalid STOP_LOSS_RATIO value")?;
+
+    // Multiple of the buy price the position must reach before the trailing stop arms
+    let trailing_activation = env::var("TRAILING_ACTIVATION")
+        .unwrap_or_else(|_| "2.0".to_string())
+        .parse::<f64>()
+        .context("Invalid TRAILING_ACTIVATION value")?;
+
+    // Percentage drawdown from the peak price that triggers the trailing stop
+    let trailing_drawdown_pct = env::var("TRAILING_DRAWDOWN_PCT")
+        .unwrap_or_else(|_| "20.0".to_string())
+        .parse::<f64>()
+        .context("Invalid TRAILING_DRAWDOWN_PCT value")?;
+
+    // Priority fee bid, in micro-lamports per compute unit
+    let priority_fee_microlamports = env::var("PRIORITY_FEE_MICROLAMPORTS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse::<u64>()
+        .context("Invalid PRIORITY_FEE_MICROLAMPORTS value")?;
+
+    // Compute unit budget requested for buy/sell transactions
+    let compute_unit_limit = env::var("COMPUTE_UNIT_LIMIT")
+        .unwrap_or_else(|_| "200000".to_string())
+        .parse::<u32>()
+        .context("Invalid COMPUTE_UNIT_LIMIT value")?;
+
+    // When enabled, bid a percentile of recent network prioritization fees
+    // instead of the static PRIORITY_FEE_MICROLAMPORTS value
+    let dynamic_priority_fee = env::var("DYNAMIC_PRIORITY_FEE")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
+    // Risk limits enforced by the pre-trade health gate in `risk.rs`
+    let max_concurrent_positions = env::var("MAX_CONCURRENT_POSITIONS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse::<usize>()
+        .context("Invalid MAX_CONCURRENT_POSITIONS value")?;
+
+    let max_total_sol_at_risk = env::var("MAX_TOTAL_SOL_AT_RISK")
+        .unwrap_or_else(|_| "1.0".to_string())
+        .parse::<f64>()
+        .context("Invalid MAX_TOTAL_SOL_AT_RISK value")?;
+
+    let min_wallet_balance_sol = env::var("MIN_WALLET_BALANCE_SOL")
+        .unwrap_or_else(|_| "0.05".to_string())
+        .parse::<f64>()
+        .context("Invalid MIN_WALLET_BALANCE_SOL value")?;
+
+    // Paper-trading mode: still quote against live routers, but simulate the
+    // swap instead of signing and broadcasting a transaction
+    let mock_swap = env::var("MOCK_SWAP")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
+    // Shared secret the `serve` JSON-RPC control channel requires on every
+    // request (besides `ping`) before dispatching a buy/sell/quit. Left
+    // unset only for local-loopback development; `serve --bind` lets this
+    // channel be exposed beyond localhost, where it controls real trades.
+    let server_auth_token = env::var("SERVER_AUTH_TOKEN").ok().filter(|s| !s.is_empty());
+    if server_auth_token.is_none() {
+        warn!("SERVER_AUTH_TOKEN is not set - the control server will accept unauthenticated requests");
+    }
+
     Ok(Config {
         rpc_url,
         ws_url,
+        rpc_endpoints,
+        parallel_rpc_requests,
         buy_amount_sol,
         slippage_bps,
         profit_target_1,
         profit_target_2,
         sell_percentage_1,
         sell_percentage_2,
+        stop_loss_ratio,
+        trailing_activation,
+        trailing_drawdown_pct,
+        priority_fee_microlamports,
+        compute_unit_limit,
+        dynamic_priority_fee,
+        max_concurrent_positions,
+        max_total_sol_at_risk,
+        min_wallet_balance_sol,
+        mock_swap,
+        server_auth_token,
     })
 }
 
@@ -1,12 +1,23 @@
-use std::env;
+use std::sync::Arc;
 use anyhow::Result;
-use log::{info, error, LevelFilter};
-use dotenv::dotenv;
-use solana_sdk::signature::{Keypair, Signer};
-use solana_client::rpc_client::RpcClient;
+use clap::Parser;
+use log::LevelFilter;
 
-// Constants
-const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+mod cli;
+mod config;
+mod filter;
+mod monitor;
+mod risk;
+mod router;
+mod rpc_pool;
+mod server;
+mod trader;
+mod utils;
+mod wallet;
+
+use cli::{Cli, Commands};
+use config::AppConfig;
+use rpc_pool::RpcPool;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -16,88 +27,49 @@ async fn main() -> Result<()> {
         .format_timestamp_secs()
         .init();
 
-    // Load environment variables
-    dotenv().ok();
-    
-    // Get RPC URL with fallback
-    let rpc_url = env::var("SOLANA_RPC_URL")
-        .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
-    
-    info!("Starting Solana Pump.fun Sniper Bot");
-    info!("Using RPC URL: {}", rpc_url);
-    
-    // Initialize wallet
-    let keypair = generate_or_load_wallet()?;
-    info!("Wallet loaded: {}", keypair.pubkey());
+    let cli = Cli::parse();
 
-    // Get wallet balance
-    let rpc_client = RpcClient::new(&rpc_url);
-    match rpc_client.get_balance(&keypair.pubkey()) {
-        Ok(balance) => {
-            let sol_balance = balance as f64 / 1_000_000_000.0;
-            info!("SOL Balance: {} SOL", sol_balance);
-        },
-        Err(e) => {
-            error!("Failed to get wallet balance: {}", e);
-        }
-    }
-    
-    info!("Bot initialized successfully");
-    info!("Pump.fun program ID: {}", PUMPFUN_PROGRAM_ID);
-    
-    // In a full implementation, we would start the token monitor here
-    
-    Ok(())
-}
+    // Load configuration and wallet shared by every subcommand
+    let config = config::load_config()?;
+    let keypair = wallet::generate_or_load_wallet()?;
 
-// Generate a new wallet or load an existing one
-fn generate_or_load_wallet() -> Result<Keypair> {
-    // First try to load from private key in environment
-    if let Ok(private_key) = env::var("WALLET_PRIVATE_KEY") {
-        return get_keypair_from_base58(&private_key);
-    }
-    
-    // Then try to load from file path
-    if let Ok(path) = env::var("WALLET_PATH") {
-        if std::path::Path::new(&path).exists() {
-            return Ok(solana_sdk::signature::read_keypair_file(&path)?);
+    let rpc_pool = Arc::new(RpcPool::new(config.rpc_endpoints, config.parallel_rpc_requests));
+
+    let app_config = Arc::new(AppConfig {
+        rpc_url: config.rpc_url,
+        ws_url: config.ws_url,
+        rpc_pool,
+        keypair,
+        buy_amount_sol: config.buy_amount_sol,
+        slippage_bps: config.slippage_bps,
+        profit_target_1: config.profit_target_1,
+        profit_target_2: config.profit_target_2,
+        sell_percentage_1: config.sell_percentage_1,
+        sell_percentage_2: config.sell_percentage_2,
+        stop_loss_ratio: config.stop_loss_ratio,
+        trailing_activation: config.trailing_activation,
+        trailing_drawdown_pct: config.trailing_drawdown_pct,
+        priority_fee_microlamports: config.priority_fee_microlamports,
+        compute_unit_limit: config.compute_unit_limit,
+        dynamic_priority_fee: config.dynamic_priority_fee,
+        max_concurrent_positions: config.max_concurrent_positions,
+        max_total_sol_at_risk: config.max_total_sol_at_risk,
+        min_wallet_balance_sol: config.min_wallet_balance_sol,
+        mock_swap: config.mock_swap,
+        server_auth_token: config.server_auth_token,
+    });
+
+    match cli.command {
+        Commands::Monitor => cli::run_monitor(app_config).await,
+        Commands::Buy { mint, amount, slippage } => {
+            cli::run_buy(&app_config, &mint, amount, slippage).await
+        }
+        Commands::Sell { mint, percentage } => {
+            cli::run_sell(&app_config, &mint, percentage).await
         }
+        Commands::Positions => cli::run_positions(&app_config).await,
+        Commands::Balance => cli::run_balance(&app_config).await,
+        Commands::Wallet { action } => cli::run_wallet(&app_config, action).await,
+        Commands::Serve { bind } => cli::run_serve(app_config, &bind).await,
     }
-    
-    // Otherwise, generate a new keypair
-    let keypair = Keypair::new();
-    info!("Generated new wallet: {}", keypair.pubkey());
-    
-    // Display private key in base58 for backup
-    let private_key = bs58::encode(&keypair.to_bytes()[..32]).into_string();
-    info!("IMPORTANT: Save this private key as backup: {}", private_key);
-    
-    Ok(keypair)
 }
-
-// Convert a base58 private key string to a Keypair
-fn get_keypair_from_base58(private_key: &str) -> Result<Keypair> {
-    let bytes = bs58::decode(private_key)
-        .into_vec()?;
-    
-    if bytes.len() != 64 && bytes.len() != 32 {
-        return Err(anyhow::anyhow!(
-            "Invalid private key length. Expected 32 or 64 bytes, got {}",
-            bytes.len()
-        ));
-    }
-    
-    // If we have just the private key (32 bytes), expand to full keypair format
-    let keypair_bytes = if bytes.len() == 32 {
-        let mut full_bytes = [0u8; 64];
-        full_bytes[..32].copy_from_slice(&bytes);
-        // The public key will be derived when the Keypair is constructed
-        full_bytes
-    } else {
-        let mut full_bytes = [0u8; 64];
-        full_bytes.copy_from_slice(&bytes);
-        full_bytes
-    };
-    
-    Ok(Keypair::from_bytes(&keypair_bytes)?)
-}
\ No newline at end of file
@@ -3,25 +3,43 @@ use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::sleep;
 use anyhow::{Result, Context};
+use base64::Engine;
+use borsh::BorshDeserialize;
 use log::{info, warn, error, debug};
+use futures_util::StreamExt;
 use solana_client::nonblocking::pubsub_client::PubsubClient;
-use solana_client::rpc_filter::RpcTransactionLogsFilter;
+use solana_client::rpc_config::RpcTransactionLogsFilter;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
-use solana_transaction_status::UiTransactionStatusMeta;
 
 use crate::config::{AppConfig, PUMPFUN_PROGRAM_ID};
 use crate::filter::is_token_safe;
-use crate::trader::{self, TokenPosition};
+use crate::risk::{passes_local_risk_checks, passes_wallet_balance_check};
+use crate::trader::{self, TokenPosition, PENDING_STATUS};
+use crate::utils::anchor_discriminator;
 
 // Token information extracted from a new token creation
 pub struct NewTokenInfo {
     pub mint_address: Pubkey,
+    pub bonding_curve: Pubkey,
     pub name: String,
     pub symbol: String,
+    pub uri: String,
     pub transaction_signature: String,
 }
 
+// Layout of the Anchor event Pump.fun emits from its `create` instruction
+// (after the 8-byte Anchor event discriminator), as published in the
+// program's IDL
+#[derive(BorshDeserialize)]
+struct CreateEvent {
+    name: String,
+    symbol: String,
+    uri: String,
+    mint: Pubkey,
+    bonding_curve: Pubkey,
+    _user: Pubkey,
+}
+
 // Start the monitoring process for new Pump.fun tokens
 pub async fn start_token_monitor(
     app_config: Arc<AppConfig>,
@@ -76,118 +94,93 @@ async fn subscribe_new_tokens(
     active_tokens: Arc<Mutex<Vec<TokenPosition>>>,
 ) -> Result<()> {
     // Connect to Solana websocket for logs
-    let (pubsub_client, mut receiver) = PubsubClient::logs_subscribe(
-        ws_url,
-        RpcTransactionLogsFilter::Mentions(program_id.to_string()),
+    let pubsub_client = PubsubClient::new(ws_url).await
+        .context("Failed to connect to Pump.fun websocket")?;
+    let (mut log_stream, _unsubscribe) = pubsub_client.logs_subscribe(
+        RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
         solana_client::rpc_config::RpcTransactionLogsConfig {
             commitment: Some(solana_sdk::commitment_config::CommitmentConfig::confirmed()),
         },
     ).await.context("Failed to subscribe to Pump.fun program logs")?;
-    
+
     info!("Successfully subscribed to Pump.fun program logs");
-    
+
     // Process incoming log messages
-    while let Some(log_notification) = receiver.recv().await {
+    while let Some(log_notification) = log_stream.next().await {
         let sig = log_notification.value.signature.clone();
         debug!("Received transaction: {}", sig);
         
         // Check if the log corresponds to a 'create' instruction
-        if let Some(logs) = &log_notification.value.logs {
-            if logs.iter().any(|line| line.contains("create")) {
-                info!("Potential new token creation detected! Tx: {}", sig);
-                
-                // Parse transaction to extract token information
-                match extract_token_info_from_logs(logs, sig.clone()).await {
-                    Ok(Some(token_info)) => {
-                        info!("New token found: {} ({})", token_info.name, token_info.mint_address);
-                        
-                        // Spawn a task to handle this token (filter, buy, monitor)
-                        let task_app_config = app_config.clone();
-                        let task_active_tokens = active_tokens.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = handle_new_token(token_info, task_app_config, task_active_tokens).await {
-                                error!("Failed to process new token: {}", e);
-                            }
-                        });
-                    },
-                    Ok(None) => {
-                        debug!("Transaction {} did not contain valid token creation", sig);
-                    },
-                    Err(e) => {
-                        warn!("Failed to extract token info from transaction {}: {}", sig, e);
-                    }
+        let logs = &log_notification.value.logs;
+        if logs.iter().any(|line| line.contains("create")) {
+            info!("Potential new token creation detected! Tx: {}", sig);
+
+            // Parse transaction to extract token information
+            match extract_token_info_from_logs(logs, sig.clone()).await {
+                Ok(Some(token_info)) => {
+                    info!("New token found: {} ({})", token_info.name, token_info.mint_address);
+
+                    // Spawn a task to handle this token (filter, buy, monitor)
+                    let task_app_config = app_config.clone();
+                    let task_active_tokens = active_tokens.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_new_token(token_info, task_app_config, task_active_tokens).await {
+                            error!("Failed to process new token: {}", e);
+                        }
+                    });
+                },
+                Ok(None) => {
+                    debug!("Transaction {} did not contain valid token creation", sig);
+                },
+                Err(e) => {
+                    warn!("Failed to extract token info from transaction {}: {}", sig, e);
                 }
             }
         }
     }
     
-    // Drop the pubsub client to close the connection
-    drop(pubsub_client);
-    
     Ok(())
 }
 
-// Extract token information from transaction logs
+// Extract token information from transaction logs by finding and
+// deserializing the Anchor `CreateEvent` Pump.fun emits via `sol_log_data`,
+// rather than pattern-matching the human-readable program log lines (which
+// are not a stable interface and can be reformatted without notice)
 async fn extract_token_info_from_logs(
     logs: &[String],
     signature: String,
 ) -> Result<Option<NewTokenInfo>> {
-    // Extract mint address from logs
-    // This is a simplified implementation and may need adjustment based on actual log format
-    
-    let mut mint_address = None;
-    let mut name = None;
-    let mut symbol = None;
-    
+    let discriminator = anchor_discriminator("event", "CreateEvent");
+
     for log in logs {
-        // Look for mint address in logs
-        if log.contains("mint:") {
-            // Parse out the mint address
-            if let Some(mint_str) = log.split("mint:").nth(1) {
-                let mint_str = mint_str.trim();
-                if mint_str.len() >= 32 {  // Simple validation for Solana address length
-                    match mint_str.parse::<Pubkey>() {
-                        Ok(pubkey) => mint_address = Some(pubkey),
-                        Err(_) => continue,
-                    }
-                }
-            }
-        }
-        
-        // Look for token name
-        if log.contains("name:") {
-            if let Some(name_str) = log.split("name:").nth(1) {
-                name = Some(name_str.trim().to_string());
-            }
-        }
-        
-        // Look for token symbol
-        if log.contains("symbol:") {
-            if let Some(symbol_str) = log.split("symbol:").nth(1) {
-                symbol = Some(symbol_str.trim().to_string());
-            }
-        }
-    }
-    
-    // If we couldn't extract the information from logs, try fetching the transaction
-    if mint_address.is_none() || name.is_none() || symbol.is_none() {
-        // This would require parsing the transaction data
-        // For simplicity, we'll use placeholder values when missing
-        if mint_address.is_none() {
-            warn!("Could not extract mint address from logs");
-            return Ok(None);
+        let Some(encoded) = log.strip_prefix("Program data: ") else {
+            continue;
+        };
+
+        let data = match base64::engine::general_purpose::STANDARD.decode(encoded.trim()) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        if data.len() < 8 || data[..8] != discriminator {
+            continue;
         }
-        
-        name = name.or_else(|| Some("Unknown Token".to_string()));
-        symbol = symbol.or_else(|| Some("UNKNOWN".to_string()));
+
+        let event = CreateEvent::try_from_slice(&data[8..])
+            .context("Failed to deserialize CreateEvent")?;
+
+        return Ok(Some(NewTokenInfo {
+            mint_address: event.mint,
+            bonding_curve: event.bonding_curve,
+            name: event.name,
+            symbol: event.symbol,
+            uri: event.uri,
+            transaction_signature: signature,
+        }));
     }
-    
-    Ok(Some(NewTokenInfo {
-        mint_address: mint_address.unwrap(),
-        name: name.unwrap_or_else(|| "Unknown Token".to_string()),
-        symbol: symbol.unwrap_or_else(|| "UNKNOWN".to_string()),
-        transaction_signature: signature,
-    }))
+
+    debug!("Transaction {} had no CreateEvent in its logs", signature);
+    Ok(None)
 }
 
 // Handle a new token (filter, buy, monitor)
@@ -197,27 +190,52 @@ async fn handle_new_token(
     active_tokens: Arc<Mutex<Vec<TokenPosition>>>,
 ) -> Result<()> {
     // Apply filtering to check if the token is likely to be safe
-    if !is_token_safe(&token_info).await {
+    if !is_token_safe(&token_info).await? {
         warn!("Token {} ({}) did not pass safety filters, skipping", 
               token_info.name, token_info.mint_address);
         return Ok(());
     }
     
-    info!("Token {} ({}) passed safety filters, attempting to buy", 
+    info!("Token {} ({}) passed safety filters, checking risk limits",
           token_info.name, token_info.mint_address);
-    
-    // Execute buy order via Jupiter
+
+    // Reserve a slot for this mint atomically with the local (dedupe /
+    // concurrent-position / SOL-at-risk) checks, by pushing a pending
+    // placeholder position before releasing the lock. Without this, two
+    // tasks racing for the same mint (or a burst of new tokens racing the
+    // caps) could both see a snapshot that passes every check and both buy.
+    {
+        let mut tokens = active_tokens.lock().await;
+        if !passes_local_risk_checks(&token_info, &app_config, &tokens) {
+            return Ok(());
+        }
+        tokens.push(pending_position(&token_info, &app_config));
+    }
+
+    if !passes_wallet_balance_check(&token_info, &app_config).await? {
+        remove_pending_position(&active_tokens, &token_info.mint_address).await;
+        return Ok(());
+    }
+
+    info!("Token {} ({}) passed risk limits, attempting to buy",
+          token_info.name, token_info.mint_address);
+
+    // Execute buy order via the router fallback chain
+    let priority_fee = app_config.resolved_priority_fee(&app_config.rpc_url);
     match trader::buy_token(
-        &app_config.rpc_url,
+        &app_config.rpc_pool,
         &app_config.keypair,
         &token_info.mint_address,
         app_config.buy_amount_sol,
         app_config.slippage_bps,
+        priority_fee,
+        app_config.compute_unit_limit,
+        app_config.mock_swap,
     ).await {
         Ok(buy_info) => {
-            info!("Successfully bought {} ({}) for {} SOL", 
+            info!("Successfully bought {} ({}) for {} SOL",
                   token_info.name, token_info.mint_address, buy_info.sol_amount);
-            
+
             // Create a new token position and add to active tokens
             let position = TokenPosition {
                 mint_address: token_info.mint_address,
@@ -227,6 +245,7 @@ async fn handle_new_token(
                 buy_amount_sol: buy_info.sol_amount,
                 token_amount: buy_info.token_amount,
                 current_price: buy_info.token_price,
+                peak_price: buy_info.token_price,
                 buy_time: chrono::Utc::now(),
                 profit_target_1: app_config.profit_target_1 * buy_info.token_price,
                 profit_target_2: app_config.profit_target_2 * buy_info.token_price,
@@ -234,92 +253,204 @@ async fn handle_new_token(
                 last_updated: chrono::Utc::now(),
                 status: "Active".to_string(),
             };
-            
-            // Add to active tokens
-            active_tokens.lock().await.push(position);
-            
+
+            // Replace the pending reservation with the finalized position
+            let mut tokens = active_tokens.lock().await;
+            match tokens.iter_mut().find(|p| p.mint_address == token_info.mint_address && p.status == PENDING_STATUS) {
+                Some(slot) => *slot = position,
+                None => tokens.push(position),
+            }
+            if let Err(e) = crate::utils::save_positions(&tokens) {
+                warn!("Failed to persist positions: {}", e);
+            }
+
             Ok(())
         },
         Err(e) => {
-            error!("Failed to buy token {} ({}): {}", 
+            error!("Failed to buy token {} ({}): {}",
                    token_info.name, token_info.mint_address, e);
+            remove_pending_position(&active_tokens, &token_info.mint_address).await;
             Err(e)
         }
     }
 }
 
-// Monitor prices of active tokens and execute sell orders when targets are hit
+// Build the placeholder position pushed to reserve a mint's slot while the
+// wallet-balance check and buy are still in flight. Its `buy_amount_sol`
+// counts toward the SOL-at-risk cap and its `sold_percentage` of 0.0 counts
+// toward the concurrent-position cap and dedupe check, just like a real
+// position would.
+fn pending_position(token_info: &NewTokenInfo, app_config: &AppConfig) -> TokenPosition {
+    TokenPosition {
+        mint_address: token_info.mint_address,
+        name: token_info.name.clone(),
+        symbol: token_info.symbol.clone(),
+        buy_price: 0.0,
+        buy_amount_sol: app_config.buy_amount_sol,
+        token_amount: 0.0,
+        current_price: 0.0,
+        peak_price: 0.0,
+        buy_time: chrono::Utc::now(),
+        profit_target_1: 0.0,
+        profit_target_2: 0.0,
+        sold_percentage: 0.0,
+        last_updated: chrono::Utc::now(),
+        status: PENDING_STATUS.to_string(),
+    }
+}
+
+// Drop a mint's reservation after the wallet-balance check or the buy
+// itself fails, so it doesn't linger and permanently occupy a concurrent-
+// position/SOL-at-risk slot
+async fn remove_pending_position(active_tokens: &Arc<Mutex<Vec<TokenPosition>>>, mint_address: &Pubkey) {
+    let mut tokens = active_tokens.lock().await;
+    tokens.retain(|p| !(p.mint_address == *mint_address && p.status == PENDING_STATUS));
+}
+
+// Monitor prices of active tokens and execute sell orders when targets are hit.
+// Snapshots the token list and drops the lock before doing any per-token
+// price fetch or sell call, then re-locks only to merge the results back -
+// mirroring the passes_local_risk_checks/passes_wallet_balance_check
+// split in handle_new_token. Without this, a slow RPC call for one token
+// would hold the lock across every other tracked token's price update and
+// stop-loss/trailing-stop check for the duration.
 async fn monitor_token_prices(
     app_config: Arc<AppConfig>,
     active_tokens: Arc<Mutex<Vec<TokenPosition>>>,
 ) -> Result<()> {
-    let mut tokens = active_tokens.lock().await;
-    
+    let mut tokens = active_tokens.lock().await.clone();
+
     // Skip if no active tokens
     if tokens.is_empty() {
         return Ok(());
     }
-    
+
     // Update current prices and check sell targets
+    let mut positions_changed = false;
     for token in tokens.iter_mut() {
         if token.sold_percentage >= 100.0 {
             // Skip tokens that are fully sold
             continue;
         }
         
-        // Update current price via Jupiter API
+        // Update current price via the router fallback chain
         match trader::get_token_price(
-            &app_config.rpc_url,
+            &app_config.rpc_pool,
             &token.mint_address,
         ).await {
             Ok(current_price) => {
                 token.current_price = current_price;
                 token.last_updated = chrono::Utc::now();
-                
+                if current_price > token.peak_price {
+                    token.peak_price = current_price;
+                }
+
                 let price_ratio = current_price / token.buy_price;
                 debug!("{} price: {} SOL ({}x)", token.name, current_price, price_ratio);
-                
-                // Check if price targets are hit
-                if token.sold_percentage < app_config.sell_percentage_1 && price_ratio >= app_config.profit_target_1 {
-                    info!("First profit target hit for {} ({}x) - selling {}%",
-                          token.name, price_ratio, app_config.sell_percentage_1);
-                    
-                    // Calculate amount to sell
-                    let sell_amount = token.token_amount * (app_config.sell_percentage_1 / 100.0);
-                    
-                    // Execute sell
+
+                // Hard stop-loss: bail out entirely if the price has collapsed
+                // below a fraction of the buy price, regardless of profit targets
+                let stop_loss_price = token.buy_price * app_config.stop_loss_ratio;
+                // Trailing stop: once the position has ever run up past the
+                // activation multiple, lock in gains by selling on a drawdown
+                // from the peak rather than riding it back down to zero
+                let trailing_armed = token.peak_price >= token.buy_price * app_config.trailing_activation;
+                let trailing_stop_price = token.peak_price * (1.0 - app_config.trailing_drawdown_pct / 100.0);
+
+                if current_price <= stop_loss_price {
+                    info!("Stop-loss hit for {} (price {} <= {}) - selling remaining position",
+                          token.name, current_price, stop_loss_price);
+
+                    let remaining_percentage = 100.0 - token.sold_percentage;
+                    let sell_amount = token.token_amount * (remaining_percentage / 100.0);
+
                     if let Err(e) = trader::sell_token(
-                        &app_config.rpc_url,
+                        &app_config.rpc_pool,
                         &app_config.keypair,
                         &token.mint_address,
                         sell_amount as u64,
                         app_config.slippage_bps,
+                        app_config.resolved_priority_fee(&app_config.rpc_url),
+                        app_config.compute_unit_limit,
+                        app_config.mock_swap,
+                    ).await {
+                        error!("Failed to sell {} at stop-loss: {}", token.name, e);
+                    } else {
+                        token.sold_percentage = 100.0;
+                        token.status = "Stopped Out".to_string();
+                        positions_changed = true;
+                    }
+                } else if trailing_armed && current_price <= trailing_stop_price {
+                    info!("Trailing stop hit for {} (price {} <= {} off peak {}) - selling remaining position",
+                          token.name, current_price, trailing_stop_price, token.peak_price);
+
+                    let remaining_percentage = 100.0 - token.sold_percentage;
+                    let sell_amount = token.token_amount * (remaining_percentage / 100.0);
+
+                    if let Err(e) = trader::sell_token(
+                        &app_config.rpc_pool,
+                        &app_config.keypair,
+                        &token.mint_address,
+                        sell_amount as u64,
+                        app_config.slippage_bps,
+                        app_config.resolved_priority_fee(&app_config.rpc_url),
+                        app_config.compute_unit_limit,
+                        app_config.mock_swap,
+                    ).await {
+                        error!("Failed to sell {} at trailing stop: {}", token.name, e);
+                    } else {
+                        token.sold_percentage = 100.0;
+                        token.status = "Trailing Stop".to_string();
+                        positions_changed = true;
+                    }
+                } else if token.sold_percentage < app_config.sell_percentage_1 && price_ratio >= app_config.profit_target_1 {
+                    info!("First profit target hit for {} ({}x) - selling {}%",
+                          token.name, price_ratio, app_config.sell_percentage_1);
+
+                    // Target the exact SOL proceeds for this slice of the
+                    // position at the current price, rather than estimating
+                    // a token amount up front and hoping the fill matches
+                    let target_sol_out = token.buy_amount_sol * (app_config.sell_percentage_1 / 100.0) * price_ratio;
+
+                    if let Err(e) = trader::sell_token_exact_out(
+                        &app_config.rpc_pool,
+                        &app_config.keypair,
+                        &token.mint_address,
+                        target_sol_out,
+                        app_config.slippage_bps,
+                        app_config.resolved_priority_fee(&app_config.rpc_url),
+                        app_config.compute_unit_limit,
+                        app_config.mock_swap,
                     ).await {
                         error!("Failed to sell {} at first target: {}", token.name, e);
                     } else {
                         token.sold_percentage = app_config.sell_percentage_1;
                         token.status = format!("Sold {}%", token.sold_percentage);
+                        positions_changed = true;
                     }
                 } else if token.sold_percentage < app_config.sell_percentage_2 && price_ratio >= app_config.profit_target_2 {
                     info!("Second profit target hit for {} ({}x) - selling remaining",
                           token.name, price_ratio);
-                    
-                    // Calculate remaining amount to sell
+
+                    // Same exact-SOL targeting for the remaining slice of the position
                     let remaining_percentage = app_config.sell_percentage_2 - token.sold_percentage;
-                    let sell_amount = token.token_amount * (remaining_percentage / 100.0);
-                    
-                    // Execute sell
-                    if let Err(e) = trader::sell_token(
-                        &app_config.rpc_url,
+                    let target_sol_out = token.buy_amount_sol * (remaining_percentage / 100.0) * price_ratio;
+
+                    if let Err(e) = trader::sell_token_exact_out(
+                        &app_config.rpc_pool,
                         &app_config.keypair,
                         &token.mint_address,
-                        sell_amount as u64,
+                        target_sol_out,
                         app_config.slippage_bps,
+                        app_config.resolved_priority_fee(&app_config.rpc_url),
+                        app_config.compute_unit_limit,
+                        app_config.mock_swap,
                     ).await {
                         error!("Failed to sell {} at second target: {}", token.name, e);
                     } else {
                         token.sold_percentage = app_config.sell_percentage_2;
                         token.status = "Fully Sold".to_string();
+                        positions_changed = true;
                     }
                 }
             },
@@ -328,6 +459,27 @@ async fn monitor_token_prices(
             }
         }
     }
-    
+
+    // Re-lock only to merge this pass's results back by mint: the refreshed
+    // price and running peak for every entry still tracked, and the whole
+    // entry only for positions this pass sold from. Entries other tasks
+    // added or removed while this snapshot's RPC calls were in flight are
+    // left alone.
+    let mut live_tokens = active_tokens.lock().await;
+    for token in tokens.into_iter() {
+        if let Some(slot) = live_tokens.iter_mut().find(|p| p.mint_address == token.mint_address) {
+            slot.current_price = token.current_price;
+            slot.peak_price = slot.peak_price.max(token.peak_price);
+            if token.sold_percentage > slot.sold_percentage {
+                *slot = token;
+            }
+        }
+    }
+    if positions_changed {
+        if let Err(e) = crate::utils::save_positions(&live_tokens) {
+            warn!("Failed to persist positions: {}", e);
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use log::warn;
+use solana_sdk::signature::Signer;
+
+use crate::config::AppConfig;
+use crate::monitor::NewTokenInfo;
+use crate::trader::TokenPosition;
+use crate::wallet;
+
+// Per-mint dedupe, concurrent-position cap, and SOL-at-risk cap: cheap,
+// synchronous checks against the in-memory position list. The caller is
+// expected to hold the `active_tokens` lock across this call *and* the
+// reservation it pushes on success, so a second `handle_new_token` task
+// racing on the same mint (duplicate `create` logs) or a burst of new
+// tokens sees the reservation instead of racing past these checks too.
+pub fn passes_local_risk_checks(
+    token_info: &NewTokenInfo,
+    app_config: &AppConfig,
+    open_positions: &[TokenPosition],
+) -> bool {
+    // Per-mint dedupe: duplicate `create` logs for the same mint can reach
+    // us before the first buy for it has landed
+    if open_positions.iter().any(|p| p.mint_address == token_info.mint_address && p.sold_percentage < 100.0) {
+        warn!("Already holding a position in {} ({}), skipping duplicate buy",
+              token_info.name, token_info.mint_address);
+        return false;
+    }
+
+    // Max concurrent open positions
+    let open_count = open_positions.iter().filter(|p| p.sold_percentage < 100.0).count();
+    if open_count >= app_config.max_concurrent_positions {
+        warn!("At max concurrent positions ({}/{}), skipping buy of {}",
+              open_count, app_config.max_concurrent_positions, token_info.name);
+        return false;
+    }
+
+    // Max total SOL at risk across open positions plus this buy
+    let sol_at_risk: f64 = open_positions.iter()
+        .filter(|p| p.sold_percentage < 100.0)
+        .map(|p| p.buy_amount_sol * (1.0 - p.sold_percentage / 100.0))
+        .sum();
+    let sol_at_risk_after_buy = sol_at_risk + app_config.buy_amount_sol;
+    if sol_at_risk_after_buy > app_config.max_total_sol_at_risk {
+        warn!("Buying {} would put {:.4} SOL at risk, above the {:.4} SOL cap, skipping",
+              token_info.name, sol_at_risk_after_buy, app_config.max_total_sol_at_risk);
+        return false;
+    }
+
+    true
+}
+
+// Minimum remaining wallet balance floor, queried live so fees and other
+// activity since the last buy are accounted for. Kept separate from
+// `passes_local_risk_checks` since it's an async RPC call and can't run
+// while holding the `active_tokens` lock.
+pub async fn passes_wallet_balance_check(token_info: &NewTokenInfo, app_config: &AppConfig) -> Result<bool> {
+    let wallet_balance = wallet::get_wallet_balance(&app_config.rpc_pool, &app_config.keypair.pubkey()).await?;
+    if wallet_balance - app_config.buy_amount_sol < app_config.min_wallet_balance_sol {
+        warn!("Buying {} would drop wallet balance below the {:.4} SOL floor ({:.4} SOL available), skipping",
+              token_info.name, app_config.min_wallet_balance_sol, wallet_balance);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
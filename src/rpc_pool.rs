@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use solana_client::rpc_client::RpcClient;
+
+// Shared pool of RPC endpoints used wherever the bot used to construct a
+// single `RpcClient::new(rpc_url)`. `dispatch` races a call across up to
+// `parallelism` endpoints at once, taking whichever succeeds first, and
+// fails over to the remaining endpoints if that whole batch comes back
+// rate-limited or times out; the wallet's SOL and token balance lookups go
+// through it. Transaction sends and their blockhash fetches use `client()`,
+// a single round-robin endpoint with no failover, and the swap routers (and
+// so price lookups) use `primary_url()` only.
+pub struct RpcPool {
+    endpoints: Vec<String>,
+    parallelism: usize,
+    cursor: AtomicUsize,
+}
+
+impl RpcPool {
+    pub fn new(endpoints: Vec<String>, parallelism: usize) -> Self {
+        assert!(!endpoints.is_empty(), "RpcPool requires at least one endpoint");
+        let parallelism = parallelism.max(1).min(endpoints.len());
+        RpcPool { endpoints, parallelism, cursor: AtomicUsize::new(0) }
+    }
+
+    // The endpoint websocket subscriptions and anything not yet pool-ified
+    // (e.g. router construction) should keep using directly
+    pub fn primary_url(&self) -> &str {
+        &self.endpoints[0]
+    }
+
+    // A single endpoint, picked round-robin, for call sites that just need
+    // one `RpcClient` rather than a raced/failover dispatch
+    pub fn client(&self) -> RpcClient {
+        let idx = self.cursor.fetch_add(1, Ordering::Relaxed) % self.endpoints.len();
+        RpcClient::new(self.endpoints[idx].clone())
+    }
+
+    // Rotation starting point for this call, so consecutive calls spread
+    // across endpoints instead of always racing the same leading batch
+    fn rotation(&self) -> Vec<String> {
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed) % self.endpoints.len();
+        (0..self.endpoints.len())
+            .map(|i| self.endpoints[(start + i) % self.endpoints.len()].clone())
+            .collect()
+    }
+
+    // Run `f` against up to `parallelism` endpoints at once, returning
+    // whichever responds first. If an entire batch fails (rate limit,
+    // timeout, dead endpoint), move on to the next batch of endpoints.
+    pub fn dispatch<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: Fn(&RpcClient) -> Result<T> + Send + Sync + 'static,
+    {
+        let endpoints = self.rotation();
+        let f = Arc::new(f);
+        let mut last_err = None;
+
+        for batch in endpoints.chunks(self.parallelism) {
+            let (tx, rx) = mpsc::channel();
+
+            let handles: Vec<_> = batch
+                .iter()
+                .cloned()
+                .map(|url| {
+                    let tx = tx.clone();
+                    let f = f.clone();
+                    thread::spawn(move || {
+                        let client = RpcClient::new(url.clone());
+                        let result = f(&client);
+                        if let Err(ref e) = result {
+                            warn!("RPC call against {} failed: {}", url, e);
+                        }
+                        let _ = tx.send(result);
+                    })
+                })
+                .collect();
+            drop(tx);
+
+            for result in rx {
+                match result {
+                    Ok(value) => return Ok(value),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+
+            for handle in handles {
+                let _ = handle.join();
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| anyhow!("RpcPool has no endpoints configured")))
+    }
+}
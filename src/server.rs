@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, Notify};
+
+use crate::config::AppConfig;
+use crate::trader::{self, TokenPosition};
+use crate::utils;
+use crate::wallet;
+
+// Largest JSON-RPC request body this control server will read. Generous
+// for any real buy/sell/list_positions call, but small enough that a
+// client-supplied Content-Length can't be used to force an arbitrarily
+// large allocation before a single byte of the body has been validated.
+const MAX_CONTENT_LENGTH: usize = 1 << 20; // 1 MiB
+
+// Minimal JSON-RPC 2.0 server exposing the same buy/sell/positions/balance
+// surface the TUI renders, so the bot can run headless on a server while a
+// separate client or script drives it (or runs automated integration
+// tests) without screen-scraping the terminal.
+pub async fn run_server(
+    app_config: Arc<AppConfig>,
+    active_tokens: Arc<Mutex<Vec<TokenPosition>>>,
+    bind_addr: &str,
+) -> Result<()> {
+    let listener = TcpListener::bind(bind_addr).await
+        .with_context(|| format!("Failed to bind control server to {}", bind_addr))?;
+    info!("JSON-RPC control server listening on {}", bind_addr);
+
+    let shutdown = Arc::new(Notify::new());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted.context("Failed to accept control server connection")?;
+                let app_config = app_config.clone();
+                let active_tokens = active_tokens.clone();
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, app_config, active_tokens, shutdown).await {
+                        warn!("Control server connection from {} failed: {}", peer, e);
+                    }
+                });
+            }
+            _ = shutdown.notified() => {
+                info!("Control server received a quit request, shutting down");
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Handle a single request/response round trip: a bare `POST / HTTP/1.1`
+// carrying a JSON-RPC 2.0 body. No keep-alive, no other HTTP methods -
+// this is a control channel, not a general-purpose web server.
+async fn handle_connection(
+    mut stream: TcpStream,
+    app_config: Arc<AppConfig>,
+    active_tokens: Arc<Mutex<Vec<TokenPosition>>>,
+    shutdown: Arc<Notify>,
+) -> Result<()> {
+    // Read headers and body off the same buffered reader - splitting them
+    // across a scoped BufReader and the raw stream drops whatever body
+    // bytes the reader had already buffered ahead of the blank line,
+    // leaving read_exact waiting on bytes that already arrived and hanging
+    // the connection.
+    let mut reader = BufReader::new(&mut stream);
+    let mut content_length = 0usize;
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.context("Failed to read request header")?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.split_once(':') {
+            if value.0.eq_ignore_ascii_case("content-length") {
+                content_length = value.1.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if content_length > MAX_CONTENT_LENGTH {
+        return Err(anyhow!(
+            "Request body of {} bytes exceeds the {} byte limit", content_length, MAX_CONTENT_LENGTH
+        ));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await.context("Failed to read request body")?;
+
+    let request: Value = serde_json::from_slice(&body).context("Invalid JSON-RPC request body")?;
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let response = match authorize(method, &request, &app_config) {
+        Err(e) => json!({ "jsonrpc": "2.0", "error": { "code": -32000, "message": e.to_string() }, "id": id }),
+        Ok(()) => match dispatch(method, params, &app_config, &active_tokens, &shutdown).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(e) => json!({ "jsonrpc": "2.0", "error": { "code": -32000, "message": e.to_string() }, "id": id }),
+        },
+    };
+
+    let body = serde_json::to_vec(&response)?;
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+// Check the shared secret before a request is allowed to dispatch anything
+// beyond a bare health check. `ping` stays open so monitoring can probe
+// liveness without a credential; every other method - including ones that
+// execute real trades or stop the process - requires a matching `auth`
+// field in the request body when SERVER_AUTH_TOKEN is configured.
+fn authorize(method: &str, request: &Value, app_config: &AppConfig) -> Result<()> {
+    if method == "ping" {
+        return Ok(());
+    }
+
+    let Some(expected) = &app_config.server_auth_token else {
+        return Ok(());
+    };
+
+    let provided = request.get("auth").and_then(Value::as_str).unwrap_or_default();
+    if provided != expected {
+        return Err(anyhow!("Missing or invalid auth credential"));
+    }
+
+    Ok(())
+}
+
+// Dispatch a single JSON-RPC method against the same shared position state
+// (and trader/wallet calls) the TUI and CLI subcommands use
+async fn dispatch(
+    method: &str,
+    params: Value,
+    app_config: &Arc<AppConfig>,
+    active_tokens: &Arc<Mutex<Vec<TokenPosition>>>,
+    shutdown: &Arc<Notify>,
+) -> Result<Value> {
+    match method {
+        "ping" => Ok(json!({ "ok": true })),
+        "list_positions" => {
+            let tokens = active_tokens.lock().await;
+            Ok(serde_json::to_value(&*tokens)?)
+        }
+        "balance" => {
+            let balance = wallet::get_wallet_balance(&app_config.rpc_pool, &app_config.keypair.pubkey()).await?;
+            Ok(json!({ "sol": balance }))
+        }
+        "buy" => {
+            let mint: String = required_param(&params, "mint")?;
+            let amount: f64 = required_param(&params, "amount")?;
+            let slippage = params.get("slippage").and_then(Value::as_u64).unwrap_or(app_config.slippage_bps);
+            let mint_address: Pubkey = mint.parse().context("Invalid mint address")?;
+
+            let buy_info = trader::buy_token(
+                &app_config.rpc_pool,
+                &app_config.keypair,
+                &mint_address,
+                amount,
+                slippage,
+                app_config.resolved_priority_fee(app_config.rpc_pool.primary_url()),
+                app_config.compute_unit_limit,
+                app_config.mock_swap,
+            ).await?;
+
+            let position = TokenPosition {
+                mint_address,
+                name: mint.clone(),
+                symbol: mint,
+                buy_price: buy_info.token_price,
+                buy_amount_sol: buy_info.sol_amount,
+                token_amount: buy_info.token_amount,
+                current_price: buy_info.token_price,
+                peak_price: buy_info.token_price,
+                buy_time: chrono::Utc::now(),
+                profit_target_1: app_config.profit_target_1 * buy_info.token_price,
+                profit_target_2: app_config.profit_target_2 * buy_info.token_price,
+                sold_percentage: 0.0,
+                last_updated: chrono::Utc::now(),
+                status: "Active".to_string(),
+            };
+
+            let mut tokens = active_tokens.lock().await;
+            tokens.push(position);
+            utils::save_positions(&tokens)?;
+
+            Ok(json!({
+                "sol_amount": buy_info.sol_amount,
+                "token_amount": buy_info.token_amount,
+                "transaction_signature": buy_info.transaction_signature,
+            }))
+        }
+        "sell" => {
+            let mint: String = required_param(&params, "mint")?;
+            let percentage = params.get("percentage").and_then(Value::as_f64).unwrap_or(100.0);
+            let mint_address: Pubkey = mint.parse().context("Invalid mint address")?;
+
+            let token_balance = wallet::get_token_balance(
+                &app_config.rpc_pool,
+                &app_config.keypair.pubkey(),
+                &mint_address,
+            ).await?;
+            let sell_amount = (token_balance as f64 * (percentage / 100.0)) as u64;
+
+            let signature = trader::sell_token(
+                &app_config.rpc_pool,
+                &app_config.keypair,
+                &mint_address,
+                sell_amount,
+                app_config.slippage_bps,
+                app_config.resolved_priority_fee(app_config.rpc_pool.primary_url()),
+                app_config.compute_unit_limit,
+                app_config.mock_swap,
+            ).await?;
+
+            let mut tokens = active_tokens.lock().await;
+            if let Some(position) = tokens.iter_mut().find(|p| p.mint_address == mint_address) {
+                position.sold_percentage = (position.sold_percentage + percentage).min(100.0);
+                if position.sold_percentage >= 100.0 {
+                    position.status = "Fully Sold".to_string();
+                }
+            }
+            utils::save_positions(&tokens)?;
+
+            Ok(json!({ "transaction_signature": signature }))
+        }
+        "quit" => {
+            shutdown.notify_one();
+            Ok(json!({ "stopping": true }))
+        }
+        other => Err(anyhow!("Unknown method: {}", other)),
+    }
+}
+
+fn required_param<T: DeserializeOwned>(params: &Value, key: &str) -> Result<T> {
+    let value = params.get(key).ok_or_else(|| anyhow!("Missing required param '{}'", key))?;
+    serde_json::from_value(value.clone()).with_context(|| format!("Invalid value for param '{}'", key))
+}
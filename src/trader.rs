@@ -1,23 +1,42 @@
-use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
 use anyhow::{Result, Context, anyhow};
 use chrono::{DateTime, Utc};
-use log::{info, warn, error, debug};
-use solana_sdk::signature::{Keypair, Signature, Signer};
+use log::{info, debug};
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::message::VersionedMessage;
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
-use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
-use spl_token::instruction as token_instruction;
-use solana_sdk::transaction::Transaction;
-use solana_sdk::instruction::Instruction;
-use jup_ag::quote::{QuoteParams, QuoteResponse};
-use jup_ag::swap::{SwapParams, SwapResponse, SwapInterfaceInner};
-use serde_json::json;
-use reqwest;
-
-// Default SOL mint address (needed for Jupiter API)
-const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+use crate::router::{self, PriorityFee, SwapMode, SwapTransaction, SOL_MINT};
+use crate::rpc_pool::RpcPool;
+use crate::utils;
+
+// Starting balance for the in-memory simulated wallet used by `MOCK_SWAP`
+const MOCK_STARTING_BALANCE_SOL: f64 = 10.0;
+
+// Simulated SOL balance tracked across mock buys/sells, so a paper-trading
+// run can exhaust its (fake) funds just like a real wallet would
+static MOCK_SOL_BALANCE: OnceLock<Mutex<f64>> = OnceLock::new();
+
+fn mock_balance() -> &'static Mutex<f64> {
+    MOCK_SOL_BALANCE.get_or_init(|| Mutex::new(MOCK_STARTING_BALANCE_SOL))
+}
+
+// Stand in for a transaction signature when `MOCK_SWAP` is enabled, so mock
+// trades are visually distinguishable from real ones in logs/positions
+fn mock_signature() -> String {
+    format!("MOCK{}", Keypair::new().pubkey())
+}
+
+// Status used to reserve a position slot for a mint between passing the
+// pre-trade risk checks and the buy actually landing, so a concurrent
+// `handle_new_token` task for the same mint (or racing for the same
+// concurrent-position/SOL-at-risk cap) sees the reservation
+pub(crate) const PENDING_STATUS: &str = "Pending";
 
 // Represents a token position
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TokenPosition {
     pub mint_address: Pubkey,
     pub name: String,
@@ -26,6 +45,7 @@ pub struct TokenPosition {
     pub buy_amount_sol: f64,
     pub token_amount: f64,
     pub current_price: f64,
+    pub peak_price: f64,
     pub buy_time: DateTime<Utc>,
     pub profit_target_1: f64,
     pub profit_target_2: f64,
@@ -40,241 +60,259 @@ pub struct BuyInfo {
     pub token_amount: f64,
     pub token_price: f64,
     pub transaction_signature: String,
+    /// Which transaction format actually landed on-chain ("legacy" or "v0"),
+    /// or "mock" for a simulated `MOCK_SWAP` buy that sent nothing
+    pub transaction_form: &'static str,
 }
 
-// Buy a token using Jupiter Aggregator
+// Buy a token, trying each configured swap router in turn until one
+// returns a usable quote (Jupiter, then Sanctum, then the pump.fun
+// bonding curve directly for mints no aggregator has indexed yet)
 pub async fn buy_token(
-    rpc_url: &str,
+    rpc_pool: &RpcPool,
     keypair: &Keypair,
     mint_address: &Pubkey,
     amount_sol: f64,
     slippage_bps: u64,
+    priority_fee_microlamports: u64,
+    compute_unit_limit: u32,
+    mock_swap: bool,
 ) -> Result<BuyInfo> {
-    let client = reqwest::Client::new();
     let wallet_pubkey = keypair.pubkey();
-    
+    let sol_mint = SOL_MINT.parse::<Pubkey>().context("Invalid SOL mint")?;
+
     info!("Preparing to buy token {} with {} SOL", mint_address, amount_sol);
-    
+
     // Convert SOL amount to lamports
     let amount_lamports = (amount_sol * 1_000_000_000.0) as u64;
-    
-    // 1. Get a swap quote from Jupiter
-    let quote_url = "https://quote-api.jup.ag/v6/quote";
-    let quote_params = json!({
-        "inputMint": SOL_MINT,
-        "outputMint": mint_address.to_string(),
-        "amount": amount_lamports,
-        "slippageBps": slippage_bps,
-        "swapMode": "ExactIn",
-        "maxAccounts": 15
-    });
-    
-    debug!("Requesting Jupiter quote...");
-    let quote_response = client.get(quote_url)
-        .query(&quote_params)
-        .send()
-        .await
-        .context("Failed to get Jupiter quote")?;
-    
-    if !quote_response.status().is_success() {
-        let error_text = quote_response.text().await?;
-        return Err(anyhow!("Jupiter quote API error: {}", error_text));
-    }
-    
-    let quote: QuoteResponse = quote_response.json().await
-        .context("Failed to parse Jupiter quote response")?;
-    
+
+    let routers = router::default_routers(rpc_pool.primary_url());
+    let (chosen_router, quote) = router::quote_with_fallback(
+        &routers,
+        &sol_mint,
+        mint_address,
+        amount_lamports,
+        slippage_bps,
+        SwapMode::ExactIn,
+    ).await?;
+
     let output_amount = quote.out_amount;
     let price_per_token = amount_sol / (output_amount as f64 / 1_000_000.0); // Assuming 6 decimals for Pump tokens
-    
-    info!("Quote received: {} SOL -> {} tokens, price: {} SOL per token", 
-          amount_sol, output_amount as f64 / 1_000_000.0, price_per_token);
-    
-    // 2. Get swap instructions from Jupiter
-    let swap_url = "https://quote-api.jup.ag/v6/swap";
-    let swap_params = json!({
-        "userPublicKey": wallet_pubkey.to_string(),
-        "quoteResponse": quote,
-        "wrapAndUnwrapSol": true,
-        "feeAccount": wallet_pubkey.to_string(),
-    });
-    
-    debug!("Requesting Jupiter swap instructions...");
-    let swap_response = client.post(swap_url)
-        .json(&swap_params)
-        .send()
-        .await
-        .context("Failed to get Jupiter swap instructions")?;
-    
-    if !swap_response.status().is_success() {
-        let error_text = swap_response.text().await?;
-        return Err(anyhow!("Jupiter swap API error: {}", error_text));
+
+    info!("{} quote: {} SOL -> {} tokens, price: {} SOL per token",
+          chosen_router.name(), amount_sol, output_amount as f64 / 1_000_000.0, price_per_token);
+
+    if mock_swap {
+        let mut balance = mock_balance().lock().unwrap();
+        if *balance < amount_sol {
+            return Err(anyhow!("Simulated wallet balance ({:.4} SOL) is insufficient for a {:.4} SOL mock buy", *balance, amount_sol));
+        }
+        *balance -= amount_sol;
+
+        info!("[MOCK] Bought {} tokens of {} for {} SOL (simulated balance now {:.4} SOL)",
+              output_amount as f64 / 1_000_000.0, mint_address, amount_sol, *balance);
+
+        return Ok(BuyInfo {
+            sol_amount: amount_sol,
+            token_amount: output_amount as f64 / 1_000_000.0,
+            token_price: price_per_token,
+            transaction_signature: mock_signature(),
+            transaction_form: "mock",
+        });
     }
-    
-    let swap: SwapResponse = swap_response.json().await
-        .context("Failed to parse Jupiter swap response")?;
-    
-    let tx_data = swap.swap_transaction;
-    
-    // 3. Execute the swap transaction
-    let rpc_client = RpcClient::new(rpc_url);
-    
-    // Deserialize the transaction
-    let tx_bytes = base64::decode(&tx_data)
-        .context("Failed to decode transaction data")?;
-    
-    let mut tx: Transaction = bincode::deserialize(&tx_bytes)
-        .context("Failed to deserialize transaction")?;
-    
-    // Sign the transaction
-    tx.try_partial_sign(&[keypair], rpc_client.get_latest_blockhash()?)
-        .context("Failed to sign transaction")?;
-    
-    // Send the transaction
-    debug!("Sending buy transaction...");
-    let signature = rpc_client.send_and_confirm_transaction_with_spinner(&tx)
-        .context("Failed to send and confirm transaction")?;
-    
-    info!("Buy transaction confirmed: {}", signature);
-    
+
+    let priority_fee = PriorityFee {
+        compute_unit_limit,
+        compute_unit_price_microlamports: priority_fee_microlamports,
+    };
+    let swap_tx = chosen_router.build_swap_ix(&wallet_pubkey, &quote, priority_fee).await
+        .context("Failed to build swap transaction")?;
+
+    let (signature, transaction_form) = execute_swap(rpc_pool, keypair, swap_tx)?;
+
+    info!("Buy transaction confirmed via {} ({} tx): {}", chosen_router.name(), transaction_form, signature);
+
     // Return buy information
     Ok(BuyInfo {
         sol_amount: amount_sol,
         token_amount: output_amount as f64 / 1_000_000.0, // Assuming 6 decimals
         token_price: price_per_token,
-        transaction_signature: signature.to_string(),
+        transaction_signature: signature,
+        transaction_form,
     })
 }
 
-// Sell a token using Jupiter Aggregator
+// Sell a token, using the same router fallback chain as `buy_token`
 pub async fn sell_token(
-    rpc_url: &str,
+    rpc_pool: &RpcPool,
     keypair: &Keypair,
     mint_address: &Pubkey,
     token_amount: u64, // Amount in token's smallest unit (e.g., for 6 decimals: 1.0 token = 1,000,000 units)
     slippage_bps: u64,
+    priority_fee_microlamports: u64,
+    compute_unit_limit: u32,
+    mock_swap: bool,
 ) -> Result<String> {
-    let client = reqwest::Client::new();
     let wallet_pubkey = keypair.pubkey();
-    
+    let sol_mint = SOL_MINT.parse::<Pubkey>().context("Invalid SOL mint")?;
+
     info!("Preparing to sell {} tokens of mint {}", token_amount as f64 / 1_000_000.0, mint_address);
-    
-    // 1. Get a swap quote from Jupiter (token -> SOL)
-    let quote_url = "https://quote-api.jup.ag/v6/quote";
-    let quote_params = json!({
-        "inputMint": mint_address.to_string(),
-        "outputMint": SOL_MINT,
-        "amount": token_amount,
-        "slippageBps": slippage_bps,
-        "swapMode": "ExactIn",
-        "maxAccounts": 15
-    });
-    
-    debug!("Requesting Jupiter quote for sell...");
-    let quote_response = client.get(quote_url)
-        .query(&quote_params)
-        .send()
-        .await
-        .context("Failed to get Jupiter quote for sell")?;
-    
-    if !quote_response.status().is_success() {
-        let error_text = quote_response.text().await?;
-        return Err(anyhow!("Jupiter quote API error for sell: {}", error_text));
-    }
-    
-    let quote: QuoteResponse = quote_response.json().await
-        .context("Failed to parse Jupiter quote response for sell")?;
-    
+
+    let routers = router::default_routers(rpc_pool.primary_url());
+    let (chosen_router, quote) = router::quote_with_fallback(
+        &routers,
+        mint_address,
+        &sol_mint,
+        token_amount,
+        slippage_bps,
+        SwapMode::ExactIn,
+    ).await?;
+
     let sol_output = quote.out_amount as f64 / 1_000_000_000.0; // Convert from lamports to SOL
-    
-    info!("Sell quote received: {} tokens -> {} SOL", 
-          token_amount as f64 / 1_000_000.0, sol_output);
-    
-    // 2. Get swap instructions from Jupiter
-    let swap_url = "https://quote-api.jup.ag/v6/swap";
-    let swap_params = json!({
-        "userPublicKey": wallet_pubkey.to_string(),
-        "quoteResponse": quote,
-        "wrapAndUnwrapSol": true,
-        "feeAccount": wallet_pubkey.to_string(),
-    });
-    
-    debug!("Requesting Jupiter swap instructions for sell...");
-    let swap_response = client.post(swap_url)
-        .json(&swap_params)
-        .send()
-        .await
-        .context("Failed to get Jupiter swap instructions for sell")?;
-    
-    if !swap_response.status().is_success() {
-        let error_text = swap_response.text().await?;
-        return Err(anyhow!("Jupiter swap API error for sell: {}", error_text));
+
+    info!("{} quote: {} tokens -> {} SOL",
+          chosen_router.name(), token_amount as f64 / 1_000_000.0, sol_output);
+
+    if mock_swap {
+        let mut balance = mock_balance().lock().unwrap();
+        *balance += sol_output;
+
+        info!("[MOCK] Sold {} tokens of {} for {} SOL (simulated balance now {:.4} SOL)",
+              token_amount as f64 / 1_000_000.0, mint_address, sol_output, *balance);
+
+        return Ok(mock_signature());
     }
-    
-    let swap: SwapResponse = swap_response.json().await
-        .context("Failed to parse Jupiter swap response for sell")?;
-    
-    let tx_data = swap.swap_transaction;
-    
-    // 3. Execute the swap transaction
-    let rpc_client = RpcClient::new(rpc_url);
-    
-    // Deserialize the transaction
-    let tx_bytes = base64::decode(&tx_data)
-        .context("Failed to decode transaction data for sell")?;
-    
-    let mut tx: Transaction = bincode::deserialize(&tx_bytes)
-        .context("Failed to deserialize transaction for sell")?;
-    
-    // Sign the transaction
-    tx.try_partial_sign(&[keypair], rpc_client.get_latest_blockhash()?)
-        .context("Failed to sign transaction for sell")?;
-    
-    // Send the transaction
-    debug!("Sending sell transaction...");
-    let signature = rpc_client.send_and_confirm_transaction_with_spinner(&tx)
-        .context("Failed to send and confirm sell transaction")?;
-    
-    info!("Sell transaction confirmed: {}", signature);
-    
-    Ok(signature.to_string())
+
+    let priority_fee = PriorityFee {
+        compute_unit_limit,
+        compute_unit_price_microlamports: priority_fee_microlamports,
+    };
+    let swap_tx = chosen_router.build_swap_ix(&wallet_pubkey, &quote, priority_fee).await
+        .context("Failed to build swap transaction")?;
+
+    let (signature, transaction_form) = execute_swap(rpc_pool, keypair, swap_tx)?;
+
+    info!("Sell transaction confirmed via {} ({} tx): {}", chosen_router.name(), transaction_form, signature);
+
+    Ok(signature)
 }
 
-// Get the current price of a token in SOL
-pub async fn get_token_price(
-    rpc_url: &str,
+// Sell just enough tokens to realize exactly `target_sol_out` SOL (ExactOut),
+// for precise take-profit exits instead of approximating the token amount to
+// sell via the current price ratio. Returns the signature alongside the
+// number of tokens actually spent, since the pump.fun fee and the slippage
+// buffer applied to the quote mean it can exceed a naive estimate.
+pub async fn sell_token_exact_out(
+    rpc_pool: &RpcPool,
+    keypair: &Keypair,
     mint_address: &Pubkey,
-) -> Result<f64> {
-    let client = reqwest::Client::new();
-    
-    // Use Jupiter Price API to get the current price
-    let price_url = "https://price.jup.ag/v4/price";
-    let price_params = json!({
-        "ids": [mint_address.to_string()],
-        "vsToken": SOL_MINT,
-    });
-    
-    let price_response = client.get(price_url)
-        .query(&price_params)
-        .send()
-        .await
-        .context("Failed to get token price from Jupiter")?;
-    
-    if !price_response.status().is_success() {
-        return Err(anyhow!("Jupiter price API error: {}", price_response.status()));
+    target_sol_out: f64,
+    slippage_bps: u64,
+    priority_fee_microlamports: u64,
+    compute_unit_limit: u32,
+    mock_swap: bool,
+) -> Result<(String, u64)> {
+    let wallet_pubkey = keypair.pubkey();
+    let sol_mint = SOL_MINT.parse::<Pubkey>().context("Invalid SOL mint")?;
+    let target_lamports = (target_sol_out * 1_000_000_000.0) as u64;
+
+    info!("Preparing to sell mint {} for exactly {} SOL", mint_address, target_sol_out);
+
+    let routers = router::default_routers(rpc_pool.primary_url());
+    let (chosen_router, quote) = router::quote_with_fallback(
+        &routers,
+        mint_address,
+        &sol_mint,
+        target_lamports,
+        slippage_bps,
+        SwapMode::ExactOut,
+    ).await?;
+
+    let token_amount = quote.in_amount;
+
+    info!("{} quote: {} tokens -> exactly {} SOL",
+          chosen_router.name(), token_amount as f64 / 1_000_000.0, target_sol_out);
+
+    if mock_swap {
+        let mut balance = mock_balance().lock().unwrap();
+        *balance += target_sol_out;
+
+        info!("[MOCK] Sold {} tokens of {} for exactly {} SOL (simulated balance now {:.4} SOL)",
+              token_amount as f64 / 1_000_000.0, mint_address, target_sol_out, *balance);
+
+        return Ok((mock_signature(), token_amount));
     }
-    
-    let price_data: serde_json::Value = price_response.json().await
-        .context("Failed to parse Jupiter price response")?;
-    
-    // Extract the price from the response
-    if let Some(data) = price_data["data"].as_object() {
-        if let Some(token_data) = data.get(&mint_address.to_string()) {
-            if let Some(price) = token_data["price"].as_f64() {
-                return Ok(price);
-            }
+
+    let priority_fee = PriorityFee {
+        compute_unit_limit,
+        compute_unit_price_microlamports: priority_fee_microlamports,
+    };
+    let swap_tx = chosen_router.build_swap_ix(&wallet_pubkey, &quote, priority_fee).await
+        .context("Failed to build swap transaction")?;
+
+    let (signature, transaction_form) = execute_swap(rpc_pool, keypair, swap_tx)?;
+
+    info!("Sell (ExactOut) transaction confirmed via {} ({} tx): {}",
+          chosen_router.name(), transaction_form, signature);
+
+    Ok((signature, token_amount))
+}
+
+// Sign and send whatever a router handed back: a prebuilt transaction from
+// an aggregator (legacy or v0, e.g. Jupiter's /swap response may reference
+// address lookup tables), or raw instructions we assemble ourselves.
+// Returns the transaction signature alongside which form was actually sent.
+fn execute_swap(rpc_pool: &RpcPool, keypair: &Keypair, swap_tx: SwapTransaction) -> Result<(String, &'static str)> {
+    match swap_tx {
+        SwapTransaction::Prebuilt(tx_data) => {
+            use base64::Engine;
+            let tx_bytes = base64::engine::general_purpose::STANDARD.decode(&tx_data)
+                .context("Failed to decode transaction data")?;
+
+            // `VersionedTransaction`'s (de)serialization is backwards
+            // compatible with legacy `Transaction` bytes, so this handles
+            // both a legacy and a v0 (address-lookup-table) swap uniformly
+            let versioned_tx: VersionedTransaction = bincode::deserialize(&tx_bytes)
+                .context("Failed to deserialize transaction")?;
+
+            let transaction_form = match &versioned_tx.message {
+                VersionedMessage::Legacy(_) => "legacy",
+                VersionedMessage::V0(_) => "v0",
+            };
+
+            // Jupiter's quote/swap round trip can take long enough that the
+            // blockhash it signed against has expired, so fetch a fresh one
+            // and re-sign rather than reusing theirs - `send_versioned_transaction_with_retry`
+            // re-fetches and re-signs again on each retry attempt
+            let message = versioned_tx.message;
+            let rpc_client = rpc_pool.client();
+
+            debug!("Sending prebuilt {} swap transaction...", transaction_form);
+            let signature = utils::send_versioned_transaction_with_retry(&rpc_client, message, keypair)?;
+
+            Ok((signature, transaction_form))
+        }
+        SwapTransaction::Instructions(instructions) => {
+            let tx = Transaction::new_with_payer(&instructions, Some(&keypair.pubkey()));
+            let rpc_client = rpc_pool.client();
+
+            debug!("Sending bonding-curve swap transaction...");
+            let signature = utils::send_transaction_with_retry(&rpc_client, &tx, &[keypair])?;
+
+            Ok((signature, "legacy"))
         }
     }
-    
-    Err(anyhow!("Failed to extract token price from Jupiter response"))
+}
+
+// Get the current price of a token in SOL, trying each configured swap
+// router in turn the same way `buy_token`/`sell_token` do. Going through
+// the router fallback chain (rather than hardcoding Jupiter's price API)
+// keeps price polling working for positions bought via the bonding-curve
+// fallback, which Jupiter has no listing for until it indexes the mint.
+pub async fn get_token_price(
+    rpc_pool: &RpcPool,
+    mint_address: &Pubkey,
+) -> Result<f64> {
+    let routers = router::default_routers(rpc_pool.primary_url());
+    router::price_with_fallback(&routers, mint_address).await
 }
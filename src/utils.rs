@@ -1,52 +1,199 @@
 use anyhow::{Result, Context};
-use log::{info, warn, error};
+use log::warn;
+use sha2::{Digest, Sha256};
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
+use solana_sdk::signature::{Signer, Keypair};
 use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcSendTransactionConfig;
-use solana_sdk::commitment_config::CommitmentConfig;
-use solana_sdk::transaction::Transaction;
+use solana_client::tpu_client::{TpuClient, TpuClientConfig};
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use std::env;
+use std::fs;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::thread::sleep;
 
+use crate::trader::TokenPosition;
+
 // Maximum retry attempts for transactions
 const MAX_RETRIES: u8 = 3;
 // Retry delay in milliseconds
 const RETRY_DELAY_MS: u64 = 500;
+// How long to wait for a TPU-submitted transaction to confirm before giving up
+const TPU_CONFIRM_TIMEOUT_SECS: u64 = 30;
+// How often to poll signature status while waiting on a TPU submission
+const TPU_POLL_INTERVAL_MS: u64 = 250;
+
+// File used to share open positions between the monitor loop and the CLI
+const POSITIONS_FILE: &str = "positions.json";
 
-// Send a transaction with automatic retry on failure
+// Send a transaction with automatic retry on failure. By default this routes
+// through the RPC node's send-and-confirm path; set `USE_TPU=1` to instead
+// forward the signed transaction straight to the current/next leader's TPU
+// port, which lands far faster than waiting on the RPC node to relay it -
+// critical when sniping for the first block after a token launches.
 pub fn send_transaction_with_retry(
     rpc_client: &RpcClient,
     transaction: &Transaction,
     signers: &[&dyn Signer],
 ) -> Result<String> {
+    let use_tpu = env::var("USE_TPU")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
     let mut last_error = None;
-    
+
     for attempt in 1..=MAX_RETRIES {
         let blockhash = rpc_client.get_latest_blockhash()
             .context("Failed to get recent blockhash")?;
-        
+
         let mut tx = transaction.clone();
         tx.sign(signers, blockhash);
-        
-        match rpc_client.send_and_confirm_transaction_with_spinner(&tx) {
+
+        let result = if use_tpu {
+            send_via_tpu(rpc_client, &tx)
+        } else {
+            rpc_client.send_and_confirm_transaction_with_spinner(&tx)
+                .map(|signature| signature.to_string())
+                .map_err(anyhow::Error::from)
+        };
+
+        match result {
             Ok(signature) => {
-                return Ok(signature.to_string());
+                return Ok(signature);
             },
             Err(err) => {
                 warn!("Transaction failed on attempt {}/{}: {}", attempt, MAX_RETRIES, err);
                 last_error = Some(err);
-                
+
                 if attempt < MAX_RETRIES {
                     sleep(Duration::from_millis(RETRY_DELAY_MS));
                 }
             }
         }
     }
-    
+
     Err(anyhow::anyhow!("Transaction failed after {} attempts: {:?}", MAX_RETRIES, last_error))
 }
 
+// Forward a signed transaction directly to the leader's TPU port and poll
+// for confirmation ourselves, since the TPU path is fire-and-forget
+fn send_via_tpu(rpc_client: &RpcClient, transaction: &Transaction) -> Result<String> {
+    let ws_url = crate::config::derive_ws_url(&rpc_client.url());
+
+    let tpu_client = TpuClient::new(
+        Arc::new(RpcClient::new(rpc_client.url())),
+        &ws_url,
+        TpuClientConfig::default(),
+    ).context("Failed to construct TPU client")?;
+
+    if !tpu_client.send_transaction(transaction) {
+        return Err(anyhow::anyhow!("TPU client failed to forward transaction to the leader"));
+    }
+
+    let signature = transaction.signatures[0];
+    let deadline = Instant::now() + Duration::from_secs(TPU_CONFIRM_TIMEOUT_SECS);
+
+    while Instant::now() < deadline {
+        if let Ok(Some(status)) = rpc_client.get_signature_status(&signature) {
+            return status
+                .map(|_| signature.to_string())
+                .map_err(|e| anyhow::anyhow!("Transaction {} failed: {}", signature, e));
+        }
+        sleep(Duration::from_millis(TPU_POLL_INTERVAL_MS));
+    }
+
+    Err(anyhow::anyhow!("Timed out waiting for TPU-submitted transaction {} to confirm", signature))
+}
+
+// Same retry/TPU behavior as `send_transaction_with_retry`, for the prebuilt
+// (possibly v0, address-lookup-table) transactions Jupiter/Sanctum hand
+// back, which every ordinary buy/sell goes through - so `USE_TPU=1` speeds
+// up that path too, not just the bonding-curve fallback's legacy `Transaction`s.
+pub fn send_versioned_transaction_with_retry(
+    rpc_client: &RpcClient,
+    message: VersionedMessage,
+    keypair: &Keypair,
+) -> Result<String> {
+    let use_tpu = env::var("USE_TPU")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
+    let mut last_error = None;
+
+    for attempt in 1..=MAX_RETRIES {
+        let blockhash = rpc_client.get_latest_blockhash()
+            .context("Failed to get recent blockhash")?;
+
+        let mut message = message.clone();
+        match &mut message {
+            VersionedMessage::Legacy(m) => m.recent_blockhash = blockhash,
+            VersionedMessage::V0(m) => m.recent_blockhash = blockhash,
+        }
+
+        let signed_tx = VersionedTransaction::try_new(message, &[keypair])
+            .context("Failed to sign versioned transaction")?;
+
+        let result = if use_tpu {
+            send_versioned_via_tpu(rpc_client, &signed_tx)
+        } else {
+            rpc_client.send_and_confirm_transaction(&signed_tx)
+                .map(|signature| signature.to_string())
+                .map_err(anyhow::Error::from)
+        };
+
+        match result {
+            Ok(signature) => {
+                return Ok(signature);
+            },
+            Err(err) => {
+                warn!("Transaction failed on attempt {}/{}: {}", attempt, MAX_RETRIES, err);
+                last_error = Some(err);
+
+                if attempt < MAX_RETRIES {
+                    sleep(Duration::from_millis(RETRY_DELAY_MS));
+                }
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!("Transaction failed after {} attempts: {:?}", MAX_RETRIES, last_error))
+}
+
+// Forward a signed versioned transaction directly to the leader's TPU port.
+// `TpuClient::send_transaction` only takes legacy `Transaction`s, so go
+// through `send_wire_transaction` with the already-serialized bytes instead,
+// which works for either transaction format.
+fn send_versioned_via_tpu(rpc_client: &RpcClient, transaction: &VersionedTransaction) -> Result<String> {
+    let ws_url = crate::config::derive_ws_url(&rpc_client.url());
+
+    let tpu_client = TpuClient::new(
+        Arc::new(RpcClient::new(rpc_client.url())),
+        &ws_url,
+        TpuClientConfig::default(),
+    ).context("Failed to construct TPU client")?;
+
+    let wire_transaction = bincode::serialize(transaction)
+        .context("Failed to serialize versioned transaction")?;
+    if !tpu_client.send_wire_transaction(wire_transaction) {
+        return Err(anyhow::anyhow!("TPU client failed to forward transaction to the leader"));
+    }
+
+    let signature = transaction.signatures[0];
+    let deadline = Instant::now() + Duration::from_secs(TPU_CONFIRM_TIMEOUT_SECS);
+
+    while Instant::now() < deadline {
+        if let Ok(Some(status)) = rpc_client.get_signature_status(&signature) {
+            return status
+                .map(|_| signature.to_string())
+                .map_err(|e| anyhow::anyhow!("Transaction {} failed: {}", signature, e));
+        }
+        sleep(Duration::from_millis(TPU_POLL_INTERVAL_MS));
+    }
+
+    Err(anyhow::anyhow!("Timed out waiting for TPU-submitted transaction {} to confirm", signature))
+}
+
 // Format lamports as SOL with appropriate precision
 pub fn format_sol_amount(lamports: u64) -> String {
     let sol = lamports as f64 / 1_000_000_000.0;
@@ -77,14 +224,66 @@ pub async fn has_token_account(
     }
 }
 
+// Estimate a competitive compute-unit price by sampling recent network
+// prioritization fees and bidding the given percentile of them
+pub fn recent_priority_fee_microlamports(rpc_client: &RpcClient, percentile: u8) -> Result<u64> {
+    let fees = rpc_client.get_recent_prioritization_fees(&[])
+        .context("Failed to fetch recent prioritization fees")?;
+
+    if fees.is_empty() {
+        return Err(anyhow::anyhow!("No recent prioritization fee data available"));
+    }
+
+    let mut values: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+    values.sort_unstable();
+
+    let index = (values.len() - 1) * percentile.min(100) as usize / 100;
+    Ok(values[index])
+}
+
+// Anchor self-CPI/instruction/event discriminators are the first 8 bytes of
+// sha256("<namespace>:<name>") (e.g. "global:buy" for an instruction in the
+// `global` namespace, "event:CreateEvent" for an emitted event)
+pub(crate) fn anchor_discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let preimage = format!("{}:{}", namespace, name);
+    let hash = Sha256::digest(preimage.as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
 // Calculate profit/loss from a trade
 pub fn calculate_profit(
-    buy_price: f64, 
-    sell_price: f64, 
+    buy_price: f64,
+    sell_price: f64,
     amount: f64,
 ) -> (f64, f64) {
     let profit_amount = (sell_price - buy_price) * amount;
     let profit_percentage = (sell_price / buy_price - 1.0) * 100.0;
-    
+
     (profit_amount, profit_percentage)
 }
+
+// Persist the current set of open positions so they can be inspected by
+// a separate CLI invocation (e.g. `positions`) while the monitor is running
+pub fn save_positions(positions: &[TokenPosition]) -> Result<()> {
+    let data = serde_json::to_string_pretty(positions)
+        .context("Failed to serialize positions")?;
+    fs::write(POSITIONS_FILE, data)
+        .context("Failed to write positions file")?;
+    Ok(())
+}
+
+// Load the last persisted set of positions, if any
+pub fn load_positions() -> Result<Vec<TokenPosition>> {
+    if !std::path::Path::new(POSITIONS_FILE).exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read_to_string(POSITIONS_FILE)
+        .context("Failed to read positions file")?;
+    let positions = serde_json::from_str(&data)
+        .context("Failed to parse positions file")?;
+
+    Ok(positions)
+}
@@ -1,56 +1,43 @@
 use std::env;
 use std::path::Path;
-use std::fs;
-use std::io::{self, Write};
 use solana_sdk::signature::{Keypair, Signer, read_keypair_file};
 use anyhow::{Result, Context};
-use log::{info, warn};
-use bs58;
+use log::info;
 
-// Get the trading keypair from environment or generate a new one
-pub fn get_trading_keypair() -> Result<Keypair> {
+use crate::rpc_pool::RpcPool;
+
+// Load the wallet from env/file, or silently generate a fresh one. Never
+// prompts on stdin, so it is safe to call at startup for every CLI
+// subcommand (headless runs included).
+pub fn generate_or_load_wallet() -> Result<Keypair> {
     // First try to load from private key in environment
     if let Ok(private_key) = env::var("WALLET_PRIVATE_KEY") {
         return get_keypair_from_base58(&private_key)
             .context("Failed to parse private key from environment variable");
     }
-    
+
     // Then try to load from file path
     if let Ok(path) = env::var("WALLET_PATH") {
         if Path::new(&path).exists() {
+            // `read_keypair_file` returns `Box<dyn Error>`, which isn't
+            // `Send + Sync` and so can't implement `std::error::Error` from
+            // anyhow's perspective; stringify it before wrapping so
+            // `.context` has something it can attach to
             return read_keypair_file(&path)
-                .context("Failed to read keypair file")
-                .map_err(|e| anyhow::anyhow!(e));
+                .map_err(|e| anyhow::anyhow!(e.to_string()))
+                .context("Failed to read keypair file");
         }
     }
-    
-    // Otherwise, ask user if they want to generate new keypair
-    println!("No wallet found. Generate a new wallet? [y/N]: ");
-    io::stdout().flush()?;
-    
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let input = input.trim().to_lowercase();
-    
-    if input == "y" || input == "yes" {
-        let keypair = Keypair::new();
-        let pubkey = keypair.pubkey();
-        info!("Generated new wallet: {}", pubkey);
-        
-        // Save to disk with proper permissions
-        let outfile = "sniper-wallet.json";
-        fs::write(outfile, keypair.to_bytes())
-            .context("Unable to write keypair")?;
-        info!("Saved new keypair to {}", outfile);
-        
-        // Also display private key in base58 for backup
-        let private_key = bs58::encode(&keypair.to_bytes()[..32]).into_string();
-        info!("IMPORTANT: Save this private key as backup: {}", private_key);
-        
-        return Ok(keypair);
-    } else {
-        return Err(anyhow::anyhow!("No wallet provided. Please set WALLET_PRIVATE_KEY or WALLET_PATH environment variable."));
-    }
+
+    // Otherwise, generate a new keypair
+    let keypair = Keypair::new();
+    info!("Generated new wallet: {}", keypair.pubkey());
+
+    // Display private key in base58 for backup
+    let private_key = bs58::encode(&keypair.to_bytes()[..32]).into_string();
+    info!("IMPORTANT: Save this private key as backup: {}", private_key);
+
+    Ok(keypair)
 }
 
 // Convert a base58 private key string to a Keypair
@@ -82,40 +69,42 @@ fn get_keypair_from_base58(private_key: &str) -> Result<Keypair> {
 }
 
 // Get balance of wallet in SOL
-pub async fn get_wallet_balance(rpc_url: &str, pubkey: &solana_sdk::pubkey::Pubkey) -> Result<f64> {
-    let rpc_client = solana_client::rpc_client::RpcClient::new(rpc_url);
-    
-    let balance = rpc_client.get_balance(pubkey)
-        .context("Failed to get wallet balance")?;
-    
+pub async fn get_wallet_balance(rpc_pool: &RpcPool, pubkey: &solana_sdk::pubkey::Pubkey) -> Result<f64> {
+    let pubkey = *pubkey;
+    let balance = rpc_pool.dispatch(move |client| {
+        client.get_balance(&pubkey).context("Failed to get wallet balance")
+    })?;
+
     // Convert lamports to SOL
     let sol_balance = balance as f64 / 1_000_000_000.0;
-    
+
     Ok(sol_balance)
 }
 
 // Get token balance for a specific mint
 pub async fn get_token_balance(
-    rpc_url: &str, 
-    wallet_pubkey: &solana_sdk::pubkey::Pubkey, 
+    rpc_pool: &RpcPool,
+    wallet_pubkey: &solana_sdk::pubkey::Pubkey,
     mint_pubkey: &solana_sdk::pubkey::Pubkey
 ) -> Result<u64> {
-    let rpc_client = solana_client::rpc_client::RpcClient::new(rpc_url);
-    
     // Get the associated token account
     let token_account = spl_associated_token_account::get_associated_token_address(
         wallet_pubkey,
         mint_pubkey,
     );
-    
+
     // Check if the token account exists
-    match rpc_client.get_token_account_balance(&token_account) {
-        Ok(balance) => {
-            Ok(balance.ui_amount_string.parse::<f64>()? as u64)
-        },
-        Err(_) => {
-            // If the account doesn't exist, return 0
-            Ok(0)
+    let balance = rpc_pool.dispatch(move |client| {
+        match client.get_token_account_balance(&token_account) {
+            Ok(balance) => Ok(Some(balance.amount.clone())),
+            Err(_) => Ok(None),
         }
+    })?;
+
+    // `amount` is the raw balance in the token's smallest unit, the unit
+    // `trader::sell_token` takes
+    match balance {
+        Some(amount) => Ok(amount.parse::<u64>()?),
+        None => Ok(0),
     }
 }